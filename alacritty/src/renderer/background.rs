@@ -1,6 +1,14 @@
+use std::fs::File;
+use std::io::BufReader;
 use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::time::{Duration, Instant};
 
-use image::open;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{open, AnimationDecoder, DynamicImage, RgbImage};
 use log::warn;
 
 use crate::display::SizeInfo;
@@ -8,12 +16,130 @@ use crate::gl;
 use crate::gl::types::*;
 use crate::renderer::shader::{ShaderProgram, ShaderVersion};
 use crate::renderer::{self, CStr};
+use crate::{gl_check, gl_check_always};
+
+/// Maximum number of dual-Kawase down/upsample passes.
+///
+/// Past this the mip chain has no meaningful effect on a typical window size, so there's no
+/// point allocating more framebuffers for it.
+const MAX_BLUR_PASSES: usize = 8;
 
 #[derive(Debug)]
 struct BackgroundImage {
     pub path: String,
     pub height: u32,
     pub ratio: f32,
+
+    /// Decoded frames for an animated image, each paired with how long it stays on screen.
+    /// Empty for a still image.
+    frames: Vec<(RgbImage, Duration)>,
+    current_frame: usize,
+    /// When `current_frame` became active, used to compute when to advance.
+    frame_start: Instant,
+}
+
+/// Decode every frame of an animated GIF/WebP/APNG, paired with its display delay.
+///
+/// Returns an empty `Vec` for still images, or any format/file we fail to decode as an
+/// animation; callers should fall back to the single already-decoded frame in that case.
+fn decode_frames(path: &str) -> Vec<(RgbImage, Duration)> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let frames = match extension.as_str() {
+        "gif" => File::open(path)
+            .ok()
+            .and_then(|file| GifDecoder::new(BufReader::new(file)).ok())
+            .map(|decoder| collect_if_animated(decoder.into_frames())),
+        "webp" => File::open(path)
+            .ok()
+            .and_then(|file| WebPDecoder::new(BufReader::new(file)).ok())
+            .map(|decoder| collect_if_animated(decoder.into_frames())),
+        "png" => File::open(path).ok().and_then(|file| {
+            let decoder = PngDecoder::new(BufReader::new(file)).ok()?;
+            decoder
+                .is_apng()
+                .then(|| collect_if_animated(decoder.apng().into_frames()))
+        }),
+        _ => None,
+    };
+
+    frames.unwrap_or_default()
+}
+
+/// Collect an `AnimationDecoder`'s frames, bailing out after the second `next()` call instead
+/// of decoding every frame when there turns out to be only one — the common case for a
+/// `.gif`/`.webp`/`.png` that just isn't animated. The caller falls back to decoding the first
+/// frame through the generic still-image path in that case, so paying for more than a cheap
+/// probe here would just be a second full decode for nothing.
+fn collect_if_animated<I>(mut frames: I) -> Vec<(RgbImage, Duration)>
+where
+    I: Iterator<Item = image::ImageResult<image::Frame>>,
+{
+    let first = match frames.next() {
+        Some(Ok(frame)) => frame,
+        _ => return Vec::new(),
+    };
+    let second = match frames.next() {
+        Some(Ok(frame)) => frame,
+        _ => return Vec::new(),
+    };
+
+    let mut collected = vec![frame_delay_pair(first), frame_delay_pair(second)];
+    for frame in frames {
+        match frame {
+            Ok(frame) => collected.push(frame_delay_pair(frame)),
+            Err(_) => break,
+        }
+    }
+    collected
+}
+
+fn frame_delay_pair(frame: image::Frame) -> (RgbImage, Duration) {
+    let delay = frame.delay().into();
+    let rgb = DynamicImage::ImageRgba8(frame.into_buffer()).into_rgb8();
+    (rgb, delay)
+}
+
+/// Step `current_frame` forward by however many `delays` have fully elapsed within `elapsed`,
+/// wrapping back to the first frame past the end of the animation.
+///
+/// Returns the new current frame, the leftover time into that frame (to re-anchor
+/// `frame_start`), and whether any step was taken.
+fn advance_frame(
+    delays: &[Duration],
+    mut current_frame: usize,
+    mut elapsed: Duration,
+) -> (usize, Duration, bool) {
+    let mut changed = false;
+    while let Some(delay) = delays.get(current_frame) {
+        if elapsed < *delay {
+            break;
+        }
+        elapsed -= *delay;
+        current_frame = (current_frame + 1) % delays.len();
+        changed = true;
+    }
+    (current_frame, elapsed, changed)
+}
+
+/// How the background image is placed and scaled within the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackgroundMode {
+    /// Stretch to fill the window, ignoring aspect ratio.
+    Stretch,
+    /// Scale to cover the window, cropping the overflowing axis. The longstanding default.
+    #[default]
+    Fill,
+    /// Scale to fit entirely inside the window, letterboxing the constrained axis.
+    Fit,
+    /// Show the image at its native size, centered, letterboxing or cropping as needed.
+    Center,
+    /// Repeat the image at its native size.
+    Tile,
 }
 
 #[derive(Debug)]
@@ -21,21 +147,45 @@ pub struct BackgroundRenderer {
     // GL buffer objects.
     vao: GLuint,
     u_size_info: GLint,
+    u_uv_offset: GLint,
+    u_bg_mode: GLint,
 
     program: ShaderProgram,
     vertices: [(f32, f32, f32, f32); 6],
     texture: GLuint,
     background_image: Option<BackgroundImage>,
+    mode: BackgroundMode,
+    linear_filtering: bool,
+
+    // Dual-Kawase blur pipeline.
+    blur_down_program: ShaderProgram,
+    blur_up_program: ShaderProgram,
+    u_blur_down_texel_size: GLint,
+    u_blur_up_texel_size: GLint,
+    blur_passes: usize,
+    blur_fbo: GLuint,
+    /// Downsample/upsample chain; index 0 is full size and ends up holding the blurred result.
+    blur_textures: Vec<GLuint>,
+    blur_sizes: Vec<(i32, i32)>,
 }
 
-static HAXX: &CStr = unsafe {
-    CStr::from_bytes_with_nul_unchecked(b"sizeInfo\0")
-};
+static HAXX: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"sizeInfo\0") };
+
+static TEXEL_SIZE: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"texelSize\0") };
+
+static UV_OFFSET: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"uvOffset\0") };
+
+static BG_MODE: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"bgMode\0") };
 
 /// Shader sources for rect rendering program.
 static BG_SHADER_F: &str = include_str!("../../res/bg.f.glsl");
 static BG_SHADER_V: &str = include_str!("../../res/bg.v.glsl");
 
+/// Shader sources for the dual-Kawase blur passes; they share the background's vertex shader
+/// since every pass just draws a fullscreen quad.
+static BG_BLUR_DOWN_F: &str = include_str!("../../res/bg_blur_down.f.glsl");
+static BG_BLUR_UP_F: &str = include_str!("../../res/bg_blur_up.f.glsl");
+
 impl BackgroundRenderer {
     pub fn new(shader_version: ShaderVersion) -> Result<Self, renderer::Error> {
         let mut vao: GLuint = 0;
@@ -51,7 +201,17 @@ impl BackgroundRenderer {
 
         let program = ShaderProgram::new(shader_version, None, BG_SHADER_V, BG_SHADER_F)?;
         let u_size_info = program.get_uniform_location(HAXX)?;
+        let u_uv_offset = program.get_uniform_location(UV_OFFSET)?;
+        let u_bg_mode = program.get_uniform_location(BG_MODE)?;
+
+        let blur_down_program =
+            ShaderProgram::new(shader_version, None, BG_SHADER_V, BG_BLUR_DOWN_F)?;
+        let u_blur_down_texel_size = blur_down_program.get_uniform_location(TEXEL_SIZE)?;
+        let blur_up_program = ShaderProgram::new(shader_version, None, BG_SHADER_V, BG_BLUR_UP_F)?;
+        let u_blur_up_texel_size = blur_up_program.get_uniform_location(TEXEL_SIZE)?;
+
         let mut texture: GLuint = 0;
+        let mut blur_fbo: GLuint = 0;
         unsafe {
             // Allocate buffers.
             gl::GenVertexArrays(1, &mut vao);
@@ -89,11 +249,8 @@ impl BackgroundRenderer {
             gl::EnableVertexAttribArray(1);
 
             gl::GenTextures(1, &mut texture);
-            gl::BindTexture(gl::TEXTURE_2D, texture);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+
+            gl::GenFramebuffers(1, &mut blur_fbo);
 
             // Reset buffer bindings.
             gl::BindVertexArray(0);
@@ -101,70 +258,231 @@ impl BackgroundRenderer {
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
-        Ok(Self { vao, program, vertices, texture, u_size_info, background_image: None })
+        let renderer = Self {
+            vao,
+            program,
+            vertices,
+            texture,
+            u_size_info,
+            u_uv_offset,
+            u_bg_mode,
+            background_image: None,
+            mode: BackgroundMode::default(),
+            linear_filtering: true,
+            blur_down_program,
+            blur_up_program,
+            u_blur_down_texel_size,
+            u_blur_up_texel_size,
+            blur_passes: 0,
+            blur_fbo,
+            blur_textures: Vec::new(),
+            blur_sizes: Vec::new(),
+        };
+        renderer.apply_texture_params();
+
+        Ok(renderer)
+    }
+
+    /// Set the dual-Kawase blur strength, in number of downsample/upsample passes.
+    ///
+    /// `0` disables the blur and draws the background image directly.
+    pub fn set_blur_radius(&mut self, radius: usize) {
+        self.blur_passes = radius.min(MAX_BLUR_PASSES);
+    }
+
+    /// Set how the background image is placed and scaled within the window.
+    pub fn set_mode(&mut self, mode: BackgroundMode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.apply_texture_params();
+        }
+    }
+
+    /// Toggle bilinear filtering for scaled-up background images. Disabling this favors crisp,
+    /// aliased pixels over a smoother but blurrier upscale.
+    pub fn set_linear_filtering(&mut self, enabled: bool) {
+        if self.linear_filtering != enabled {
+            self.linear_filtering = enabled;
+            self.apply_texture_params();
+        }
+    }
+
+    /// Sync the background texture's wrap and filter parameters with `mode`/`linear_filtering`.
+    fn apply_texture_params(&self) {
+        // Only Tile wants the image to repeat; every other mode relies on `bgMode` in the
+        // fragment shader to fully control what happens outside the unit square.
+        let wrap = if self.mode == BackgroundMode::Tile {
+            gl::REPEAT
+        } else {
+            gl::CLAMP_TO_EDGE
+        };
+        let filter = if self.linear_filtering {
+            gl::LINEAR
+        } else {
+            gl::NEAREST
+        };
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
     }
 
     pub fn should_draw(&self) -> bool {
         self.background_image.is_some()
     }
 
+    /// Advance an animated background to the frame active at `now`, re-uploading its pixels to
+    /// `self.texture` when the frame changes. A no-op for still images.
+    pub fn advance(&mut self, now: Instant) {
+        let image = match &mut self.background_image {
+            Some(image) if !image.frames.is_empty() => image,
+            _ => return,
+        };
+
+        let elapsed = now.saturating_duration_since(image.frame_start);
+        let delays: Vec<Duration> = image.frames.iter().map(|(_, delay)| *delay).collect();
+        let (frame, remainder, changed) = advance_frame(&delays, image.current_frame, elapsed);
+
+        if !changed {
+            return;
+        }
+        image.current_frame = frame;
+        image.frame_start = now - remainder;
+
+        let (rgb, _) = &image.frames[image.current_frame];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl_check_always!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB as i32,
+                rgb.width() as i32,
+                rgb.height() as i32,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                rgb.as_ptr() as *const _,
+            ));
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// When the current animation frame should be swapped for the next one, so the event loop
+    /// can schedule a redraw right at that boundary instead of idling or polling. `None` for
+    /// still images, since there's nothing to animate.
+    pub fn next_frame_time(&self) -> Option<Instant> {
+        let image = self.background_image.as_ref()?;
+        let (_, delay) = image.frames.get(image.current_frame)?;
+        Some(image.frame_start + *delay)
+    }
+
     pub fn set_background(&mut self, path: &String) {
         if let Some(i) = &self.background_image {
             if &i.path == path {
                 return;
             }
         }
-        match open(path) {
-            Ok(img) => {
-                let img = img.into_rgb8();
-                self.background_image = Some(BackgroundImage {
-                    path: path.clone(),
-                    height: img.height(),
-                    ratio: img.width() as f32 / img.height() as f32,
-                });
 
-                unsafe {
-                    gl::BindTexture(gl::TEXTURE_2D, self.texture);
-                    gl::TexImage2D(
-                        gl::TEXTURE_2D,
-                        0,
-                        gl::RGB as i32,
-                        img.width() as i32,
-                        img.height() as i32,
-                        0,
-                        gl::RGB,
-                        gl::UNSIGNED_BYTE,
-                        img.as_ptr() as *const _,
-                    );
-                    gl::BindTexture(gl::TEXTURE_2D, 0);
+        let frames = decode_frames(path);
+
+        // An animated image already gives us its first frame's pixels; only fall back to the
+        // generic decoder below when there's no animation to pull them from.
+        let img = match frames.first() {
+            Some((rgb, _)) => rgb.clone(),
+            None => match open(path) {
+                Ok(img) => img.into_rgb8(),
+                Err(e) => {
+                    warn!("failed to load image ({}): {}", path, e);
+                    // still set the image so we don't try to load image at every frame
+                    self.background_image = Some(BackgroundImage {
+                        path: path.clone(),
+                        height: 0,
+                        ratio: 0f32,
+                        frames: Vec::new(),
+                        current_frame: 0,
+                        frame_start: Instant::now(),
+                    });
+                    return;
                 }
             },
-            Err(e) => {
-                warn!("failed to load image ({}): {}", path, e);
-                // still set the image so we don't try to load image at every frame
-                self.background_image = Some(BackgroundImage {
-                    path: path.clone(),
-                    height: 0,
-                    ratio: 0f32,
-                });
-            },
+        };
+
+        let upload_ok = unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            let ok = gl_check_always!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB as i32,
+                img.width() as i32,
+                img.height() as i32,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                img.as_ptr() as *const _,
+            ));
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            ok
+        };
+
+        if !upload_ok {
+            warn!(
+                "failed to upload background image ({}): GL texture upload error",
+                path
+            );
+            self.background_image = None;
+            return;
         }
+
+        self.background_image = Some(BackgroundImage {
+            path: path.clone(),
+            height: img.height(),
+            ratio: img.width() as f32 / img.height() as f32,
+            frames,
+            current_frame: 0,
+            frame_start: Instant::now(),
+        });
     }
 
-    pub fn draw(&self, size: &SizeInfo, alpha: f32) {
+    pub fn draw(&mut self, size: &SizeInfo, alpha: f32) {
+        let blurred_texture = (self.blur_passes > 0).then(|| self.draw_blurred(size));
+
         unsafe {
-            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::SRC_ALPHA, gl::ONE);
+            gl::BlendFuncSeparate(
+                gl::SRC_ALPHA,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::SRC_ALPHA,
+                gl::ONE,
+            );
             // Bind VAO to enable vertex attribute slots.
             gl::BindVertexArray(self.vao);
             gl::UseProgram(self.program.id());
-            gl::BindTexture(gl::TEXTURE_2D, self.texture);
         }
 
-        self.update_uniforms(size, alpha);
+        match blurred_texture {
+            // The blur chain already applied the background crop/scale, so the final quad just
+            // samples it back at full size.
+            Some(texture) => unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::Uniform3f(self.u_size_info, 1.0, 1.0, alpha);
+                gl::Uniform2f(self.u_uv_offset, 0.0, 0.0);
+                gl::Uniform1i(self.u_bg_mode, 0);
+            },
+            None => {
+                unsafe {
+                    gl::BindTexture(gl::TEXTURE_2D, self.texture);
+                }
+                self.update_uniforms(size, alpha);
+            }
+        }
 
         unsafe {
             // Draw all vertices as list of triangles.
-            gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32);
+            gl_check!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32));
 
             // Disable program.
             gl::BindTexture(gl::TEXTURE_2D, 0);
@@ -175,16 +493,308 @@ impl BackgroundRenderer {
         }
     }
 
-    pub fn update_uniforms(&self, size_info: &SizeInfo, alpha: f32) {
-        if let Some(img) = &self.background_image {
-            unsafe {
-                gl::Uniform3f(
-                    self.u_size_info,
-                    img.ratio * img.height as f32 / size_info.width(),
-                    img.height as f32 / size_info.height(),
-                    alpha,
+    /// Run the background image through the dual-Kawase blur chain and return the texture
+    /// holding the blurred result (always `self.blur_textures[0]`).
+    fn draw_blurred(&mut self, size: &SizeInfo) -> GLuint {
+        self.ensure_blur_targets(size);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.blur_fbo);
+
+            // Render the (cropped/scaled) background image into the full-size level.
+            let (width, height) = self.blur_sizes[0];
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                self.blur_textures[0],
+                0,
+            );
+            gl::Viewport(0, 0, width, height);
+            // Fit/Center discard outside the unit square, which would otherwise leave this
+            // texture's (uninitialized) previous contents feeding into the blur chain.
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::UseProgram(self.program.id());
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            self.update_uniforms(size, 1.0);
+            gl_check!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32));
+
+            // Downsample passes: level `i` -> level `i + 1`.
+            gl::UseProgram(self.blur_down_program.id());
+            for level in 0..self.blur_passes {
+                let (src_width, src_height) = self.blur_sizes[level];
+                let (dst_width, dst_height) = self.blur_sizes[level + 1];
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    self.blur_textures[level + 1],
+                    0,
+                );
+                gl::Viewport(0, 0, dst_width, dst_height);
+                gl::Uniform2f(
+                    self.u_blur_down_texel_size,
+                    1.0 / src_width as f32,
+                    1.0 / src_height as f32,
+                );
+                gl::BindTexture(gl::TEXTURE_2D, self.blur_textures[level]);
+                gl_check!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32));
+            }
+
+            // Upsample passes: level `i + 1` -> level `i`, back down to the full size level.
+            gl::UseProgram(self.blur_up_program.id());
+            for level in (0..self.blur_passes).rev() {
+                let (src_width, src_height) = self.blur_sizes[level + 1];
+                let (dst_width, dst_height) = self.blur_sizes[level];
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    self.blur_textures[level],
+                    0,
+                );
+                gl::Viewport(0, 0, dst_width, dst_height);
+                gl::Uniform2f(
+                    self.u_blur_up_texel_size,
+                    1.0 / src_width as f32,
+                    1.0 / src_height as f32,
                 );
+                gl::BindTexture(gl::TEXTURE_2D, self.blur_textures[level + 1]);
+                gl_check!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32));
             }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, size.width() as i32, size.height() as i32);
         }
+
+        self.blur_textures[0]
     }
-}
\ No newline at end of file
+
+    /// (Re)allocate the downsample/upsample texture chain when the pass count or window size
+    /// changed, reusing the existing textures otherwise.
+    fn ensure_blur_targets(&mut self, size: &SizeInfo) {
+        let levels = self.blur_passes + 1;
+        let mut width = size.width() as i32;
+        let mut height = size.height() as i32;
+        let sizes: Vec<(i32, i32)> = (0..levels)
+            .map(|_| {
+                let level_size = (width.max(1), height.max(1));
+                width = (width / 2).max(1);
+                height = (height / 2).max(1);
+                level_size
+            })
+            .collect();
+
+        if self.blur_sizes == sizes {
+            return;
+        }
+
+        unsafe {
+            if self.blur_textures.len() != levels {
+                if !self.blur_textures.is_empty() {
+                    gl::DeleteTextures(
+                        self.blur_textures.len() as i32,
+                        self.blur_textures.as_ptr(),
+                    );
+                }
+                self.blur_textures = vec![0; levels];
+                gl::GenTextures(levels as i32, self.blur_textures.as_mut_ptr());
+            }
+
+            for (&texture, &(level_width, level_height)) in
+                self.blur_textures.iter().zip(sizes.iter())
+            {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGB as i32,
+                    level_width,
+                    level_height,
+                    0,
+                    gl::RGB,
+                    gl::UNSIGNED_BYTE,
+                    ptr::null(),
+                );
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        self.blur_sizes = sizes;
+    }
+
+    pub fn update_uniforms(&self, size_info: &SizeInfo, alpha: f32) {
+        let img = match &self.background_image {
+            Some(img) => img,
+            None => return,
+        };
+
+        let (scale, offset, clip) = background_uv_transform(
+            self.mode,
+            size_info.width(),
+            size_info.height(),
+            img.ratio,
+            img.height,
+        );
+
+        unsafe {
+            gl::Uniform3f(self.u_size_info, scale.0, scale.1, alpha);
+            gl::Uniform2f(self.u_uv_offset, offset.0, offset.1);
+            gl::Uniform1i(self.u_bg_mode, clip as i32);
+        }
+    }
+}
+
+/// Compute the `(scale, offset, clip)` uniforms `res/bg.f.glsl` needs to place/scale the
+/// background image under `mode`.
+///
+/// `scale` is how much of the texture's `[0, 1]` range is mapped across the full quad: `scale
+/// < 1` samples a cropped sub-region (zoomed in), `scale > 1` maps the image onto only a
+/// sub-region of the quad, with `clip` discarding the rest (letterboxed) or, when `clip` is
+/// unset, the texture repeating across it (tiled). `offset` centers that region, except for
+/// Tile, whose offset is zero so the tiles start at the top-left corner.
+fn background_uv_transform(
+    mode: BackgroundMode,
+    screen_width: f32,
+    screen_height: f32,
+    image_ratio: f32,
+    image_height: u32,
+) -> ((f32, f32), (f32, f32), bool) {
+    let screen_ratio = screen_width / screen_height;
+
+    match mode {
+        BackgroundMode::Stretch => ((1.0, 1.0), (0.0, 0.0), false),
+        BackgroundMode::Fill => {
+            if image_ratio >= screen_ratio {
+                let scale_x = screen_ratio / image_ratio;
+                ((scale_x, 1.0), ((1.0 - scale_x) * 0.5, 0.0), false)
+            } else {
+                let scale_y = image_ratio / screen_ratio;
+                ((1.0, scale_y), (0.0, (1.0 - scale_y) * 0.5), false)
+            }
+        }
+        BackgroundMode::Fit => {
+            if image_ratio >= screen_ratio {
+                let scale_y = image_ratio / screen_ratio;
+                ((1.0, scale_y), (0.0, (1.0 - scale_y) * 0.5), true)
+            } else {
+                let scale_x = screen_ratio / image_ratio;
+                ((scale_x, 1.0), ((1.0 - scale_x) * 0.5, 0.0), true)
+            }
+        }
+        BackgroundMode::Center | BackgroundMode::Tile => {
+            // 1 texel = 1 pixel; this naturally crops (`scale < 1`) when the image is larger
+            // than the window and letterboxes/tiles (`scale > 1`) when it's smaller.
+            let scale_x = screen_width / (image_ratio * image_height as f32);
+            let scale_y = screen_height / image_height as f32;
+            let offset = ((1.0 - scale_x) * 0.5, (1.0 - scale_y) * 0.5);
+            match mode {
+                BackgroundMode::Center => ((scale_x, scale_y), offset, true),
+                _ => ((scale_x, scale_y), (0.0, 0.0), false),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stretch_ignores_aspect_ratio() {
+        let (scale, offset, clip) =
+            background_uv_transform(BackgroundMode::Stretch, 1920.0, 1080.0, 2.0, 100);
+        assert_eq!(scale, (1.0, 1.0));
+        assert_eq!(offset, (0.0, 0.0));
+        assert!(!clip);
+    }
+
+    #[test]
+    fn fill_crops_the_wider_image_without_clipping() {
+        // 2:1 image into a 16:9 screen: image is relatively wider, so it's cropped on X.
+        let (scale, offset, clip) =
+            background_uv_transform(BackgroundMode::Fill, 1920.0, 1080.0, 2.0, 100);
+        assert!((scale.0 - 1920.0 / 1080.0 / 2.0).abs() < 1e-6);
+        assert_eq!(scale.1, 1.0);
+        assert!((offset.0 - (1.0 - scale.0) * 0.5).abs() < 1e-6);
+        assert_eq!(offset.1, 0.0);
+        assert!(!clip);
+    }
+
+    #[test]
+    fn fit_letterboxes_the_wider_image_and_clips() {
+        // Same inputs as the Fill case above, but Fit shrinks the other axis and clips instead.
+        let (scale, offset, clip) =
+            background_uv_transform(BackgroundMode::Fit, 1920.0, 1080.0, 2.0, 100);
+        assert_eq!(scale.0, 1.0);
+        assert!((scale.1 - 2.0 / (1920.0 / 1080.0)).abs() < 1e-6);
+        assert_eq!(offset.0, 0.0);
+        assert!((offset.1 - (1.0 - scale.1) * 0.5).abs() < 1e-6);
+        assert!(clip);
+    }
+
+    #[test]
+    fn center_clips_and_offsets_by_half_the_overflow() {
+        let (scale, offset, clip) =
+            background_uv_transform(BackgroundMode::Center, 200.0, 100.0, 1.0, 100);
+        assert_eq!(scale, (2.0, 1.0));
+        assert_eq!(offset, (-0.5, 0.0));
+        assert!(clip);
+    }
+
+    #[test]
+    fn tile_never_clips_and_has_no_offset() {
+        let (scale, offset, clip) =
+            background_uv_transform(BackgroundMode::Tile, 200.0, 100.0, 1.0, 100);
+        assert_eq!(scale, (2.0, 1.0));
+        assert_eq!(offset, (0.0, 0.0));
+        assert!(!clip);
+    }
+
+    #[test]
+    fn frame_advance_steps_through_a_single_delay() {
+        let delays = [Duration::from_millis(100), Duration::from_millis(100)];
+        let (frame, remainder, changed) = advance_frame(&delays, 0, Duration::from_millis(150));
+        assert_eq!(frame, 1);
+        assert_eq!(remainder, Duration::from_millis(50));
+        assert!(changed);
+    }
+
+    #[test]
+    fn frame_advance_wraps_around_to_the_first_frame() {
+        let delays = [Duration::from_millis(100), Duration::from_millis(100)];
+        let (frame, remainder, changed) = advance_frame(&delays, 1, Duration::from_millis(100));
+        assert_eq!(frame, 0);
+        assert_eq!(remainder, Duration::ZERO);
+        assert!(changed);
+    }
+
+    #[test]
+    fn frame_advance_accumulates_across_several_delays() {
+        let delays = [
+            Duration::from_millis(30),
+            Duration::from_millis(30),
+            Duration::from_millis(30),
+        ];
+        // 70ms: past frame 0 (30ms) and frame 1 (30ms), 10ms into frame 2.
+        let (frame, remainder, changed) = advance_frame(&delays, 0, Duration::from_millis(70));
+        assert_eq!(frame, 2);
+        assert_eq!(remainder, Duration::from_millis(10));
+        assert!(changed);
+    }
+
+    #[test]
+    fn frame_advance_is_a_no_op_before_the_delay_elapses() {
+        let delays = [Duration::from_millis(100), Duration::from_millis(100)];
+        let (frame, remainder, changed) = advance_frame(&delays, 0, Duration::from_millis(50));
+        assert_eq!(frame, 0);
+        assert_eq!(remainder, Duration::from_millis(50));
+        assert!(!changed);
+    }
+}
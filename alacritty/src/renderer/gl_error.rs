@@ -0,0 +1,72 @@
+//! Helpers for surfacing `glGetError` failures instead of silently drawing nothing.
+
+use log::warn;
+
+use crate::gl;
+use crate::gl::types::*;
+
+/// Run a GL call and, in debug builds, check `glGetError` afterwards.
+///
+/// Release builds skip the check (it's a driver sync point) and assume success, matching how
+/// the rest of the renderer treats GL calls. Expands to a `bool` that is `false` when an error
+/// was logged, so callers can bail out instead of leaving a broken-but-bound resource in place.
+///
+/// Only use this for calls that run every frame (e.g. `glDrawArrays`), where the sync point
+/// would be a real cost in release builds. For anything that runs rarely (texture (re)uploads,
+/// setup), use [`gl_check_always!`] instead so failures are still caught outside debug builds.
+#[macro_export]
+macro_rules! gl_check {
+    ($op:expr) => {{
+        $op;
+        if ::std::cfg!(debug_assertions) {
+            $crate::renderer::gl_error::check(stringify!($op))
+        } else {
+            true
+        }
+    }};
+}
+
+/// Like [`gl_check!`], but checks `glGetError` in release builds too.
+///
+/// Use this for calls that don't run every frame, where the sync point is not a meaningful
+/// cost, so there's no reason to let failures go unnoticed in release builds.
+#[macro_export]
+macro_rules! gl_check_always {
+    ($op:expr) => {{
+        $op;
+        $crate::renderer::gl_error::check(stringify!($op))
+    }};
+}
+
+/// Decode a `glGetError` code into a human-readable string.
+fn describe(code: GLenum) -> &'static str {
+    match code {
+        gl::INVALID_ENUM => "invalid enum",
+        gl::INVALID_VALUE => "invalid value",
+        gl::INVALID_OPERATION => "invalid operation",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "invalid framebuffer operation",
+        gl::OUT_OF_MEMORY => "out of memory",
+        _ => "unknown GL error",
+    }
+}
+
+/// Drain the GL error queue, logging one warning per pending error.
+///
+/// Returns `true` when the queue was empty, `false` if at least one error was logged.
+pub fn check(operation: &str) -> bool {
+    let mut ok = true;
+    loop {
+        let code = unsafe { gl::GetError() };
+        if code == gl::NO_ERROR {
+            break;
+        }
+        ok = false;
+        warn!(
+            "GL error after {}: {} ({:#x})",
+            operation,
+            describe(code),
+            code
+        );
+    }
+    ok
+}
@@ -0,0 +1,284 @@
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::CStr as StdCStr;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::renderer::{self, CStr};
+
+/// GLSL version the shader sources are written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderVersion {
+    /// OpenGL 3.3 core, `#version 330 core`.
+    Glsl3,
+    /// OpenGL ES 2.0, `#version 100`.
+    Gles2,
+}
+
+/// A linked vertex+fragment GL program.
+#[derive(Debug)]
+pub struct ShaderProgram(GLuint);
+
+impl ShaderProgram {
+    /// Compile and link a program, transparently going through the on-disk binary cache.
+    pub fn new(
+        shader_version: ShaderVersion,
+        header: Option<&str>,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Self, renderer::Error> {
+        Self::from_cache_or_compile(shader_version, header, vertex_source, fragment_source)
+    }
+
+    /// Restore a previously linked program from the on-disk binary cache, falling back to
+    /// compiling and linking from source (and refreshing the cache entry) when there's no usable
+    /// cached binary.
+    ///
+    /// Program binaries are driver-specific, so the cache key is a digest of the shader sources
+    /// plus the `ShaderVersion` and the GL renderer/vendor strings; restoring a binary linked by
+    /// a different driver is expected to occasionally fail, which we detect and recover from by
+    /// recompiling from source.
+    pub fn from_cache_or_compile(
+        shader_version: ShaderVersion,
+        header: Option<&str>,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Self, renderer::Error> {
+        let cache_path = binary_cache_path(shader_version, header, vertex_source, fragment_source);
+
+        if let Some(cache_path) = &cache_path {
+            if let Some(id) = Self::restore_from_cache(cache_path) {
+                return Ok(Self(id));
+            }
+        }
+
+        let id = Self::compile(shader_version, header, vertex_source, fragment_source)?;
+
+        if let Some(cache_path) = &cache_path {
+            Self::write_to_cache(id, cache_path);
+        }
+
+        Ok(Self(id))
+    }
+
+    /// Try to restore a linked program from a cached binary blob.
+    ///
+    /// Returns `None` when there's no cache entry, the cache file is malformed, or the driver
+    /// rejects the restored binary (e.g. after a driver update).
+    fn restore_from_cache(cache_path: &Path) -> Option<GLuint> {
+        let cached = fs::read(cache_path).ok()?;
+        let format = u32::from_le_bytes(cached.get(..4)?.try_into().ok()?);
+        let binary = &cached[4..];
+
+        let id = unsafe { gl::CreateProgram() };
+        unsafe {
+            gl::ProgramBinary(id, format, binary.as_ptr() as *const _, binary.len() as i32);
+        }
+
+        if program_link_status(id) {
+            debug!(
+                "restored shader program from cache: {}",
+                cache_path.display()
+            );
+            Some(id)
+        } else {
+            warn!("cached shader program binary rejected by driver, recompiling from source");
+            unsafe { gl::DeleteProgram(id) };
+            None
+        }
+    }
+
+    /// Pull the just-linked program's binary out of the driver and persist it to `cache_path`.
+    fn write_to_cache(id: GLuint, cache_path: &Path) {
+        let mut binary_size = 0;
+        unsafe { gl::GetProgramiv(id, gl::PROGRAM_BINARY_LENGTH, &mut binary_size) };
+        if binary_size <= 0 {
+            return;
+        }
+
+        let mut binary = vec![0u8; binary_size as usize];
+        let mut length = 0;
+        let mut format: GLenum = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                id,
+                binary_size,
+                &mut length,
+                &mut format,
+                binary.as_mut_ptr() as *mut _,
+            );
+        }
+        binary.truncate(length as usize);
+
+        let mut contents = Vec::with_capacity(4 + binary.len());
+        contents.extend_from_slice(&format.to_le_bytes());
+        contents.extend_from_slice(&binary);
+
+        if let Some(parent) = cache_path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                warn!("unable to create shader cache directory: {}", err);
+                return;
+            }
+        }
+
+        if let Err(err) = fs::write(cache_path, contents) {
+            warn!("unable to write shader cache entry: {}", err);
+        }
+    }
+
+    /// Compile and link `vertex_source`/`fragment_source` from scratch.
+    fn compile(
+        shader_version: ShaderVersion,
+        header: Option<&str>,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<GLuint, renderer::Error> {
+        let vertex_shader =
+            Self::compile_stage(gl::VERTEX_SHADER, shader_version, header, vertex_source)?;
+        let fragment_shader =
+            Self::compile_stage(gl::FRAGMENT_SHADER, shader_version, header, fragment_source)?;
+
+        let program = unsafe {
+            let program = gl::CreateProgram();
+            // Allow pulling a binary blob back out once the program is linked.
+            gl::ProgramParameteri(
+                program,
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as i32,
+            );
+            gl::AttachShader(program, vertex_shader);
+            gl::AttachShader(program, fragment_shader);
+            gl::LinkProgram(program);
+            gl::DetachShader(program, vertex_shader);
+            gl::DetachShader(program, fragment_shader);
+            gl::DeleteShader(vertex_shader);
+            gl::DeleteShader(fragment_shader);
+            program
+        };
+
+        if !program_link_status(program) {
+            let log = unsafe { program_info_log(program) };
+            unsafe { gl::DeleteProgram(program) };
+            return Err(renderer::Error::ShaderCreation(log));
+        }
+
+        Ok(program)
+    }
+
+    fn compile_stage(
+        kind: GLenum,
+        shader_version: ShaderVersion,
+        header: Option<&str>,
+        source: &str,
+    ) -> Result<GLuint, renderer::Error> {
+        let version_pragma = match shader_version {
+            ShaderVersion::Glsl3 => "#version 330 core\n",
+            ShaderVersion::Gles2 => "#version 100\n",
+        };
+        let source = format!("{}{}{}", version_pragma, header.unwrap_or(""), source);
+
+        unsafe {
+            let shader = gl::CreateShader(kind);
+            let source_ptr = source.as_ptr() as *const _;
+            let length = source.len() as GLint;
+            gl::ShaderSource(shader, 1, &source_ptr, &length);
+            gl::CompileShader(shader);
+
+            let mut success: GLint = 0;
+            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+            if success == gl::TRUE as GLint {
+                Ok(shader)
+            } else {
+                let log = shader_info_log(shader);
+                gl::DeleteShader(shader);
+                Err(renderer::Error::ShaderCreation(log))
+            }
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.0
+    }
+
+    pub fn get_uniform_location(&self, name: &CStr) -> Result<GLint, renderer::Error> {
+        let location = unsafe { gl::GetUniformLocation(self.0, name.as_ptr()) };
+        if location == -1 {
+            return Err(renderer::Error::UniformNotFound(
+                name.to_string_lossy().into_owned(),
+            ));
+        }
+        Ok(location)
+    }
+}
+
+impl Drop for ShaderProgram {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgram(self.0) };
+    }
+}
+
+fn program_link_status(id: GLuint) -> bool {
+    let mut success: GLint = 0;
+    unsafe { gl::GetProgramiv(id, gl::LINK_STATUS, &mut success) };
+    success == gl::TRUE as GLint
+}
+
+unsafe fn program_info_log(id: GLuint) -> String {
+    let mut max_length: GLint = 0;
+    gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut max_length);
+    let mut buffer = vec![0u8; max_length.max(0) as usize];
+    let mut length: GLint = 0;
+    gl::GetProgramInfoLog(id, max_length, &mut length, buffer.as_mut_ptr() as *mut _);
+    buffer.truncate(length.max(0) as usize);
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+unsafe fn shader_info_log(id: GLuint) -> String {
+    let mut max_length: GLint = 0;
+    gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut max_length);
+    let mut buffer = vec![0u8; max_length.max(0) as usize];
+    let mut length: GLint = 0;
+    gl::GetShaderInfoLog(id, max_length, &mut length, buffer.as_mut_ptr() as *mut _);
+    buffer.truncate(length.max(0) as usize);
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+/// Directory the shader program binary cache is stored under.
+fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("alacritty").join("shaders"))
+}
+
+/// Digest the shader sources, the GLSL version and the driver identity (binaries are
+/// driver-specific) into the cache file path for this program.
+fn binary_cache_path(
+    shader_version: ShaderVersion,
+    header: Option<&str>,
+    vertex_source: &str,
+    fragment_source: &str,
+) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    shader_version.hash(&mut hasher);
+    header.unwrap_or("").hash(&mut hasher);
+    vertex_source.hash(&mut hasher);
+    fragment_source.hash(&mut hasher);
+    gl_string(gl::VENDOR).hash(&mut hasher);
+    gl_string(gl::RENDERER).hash(&mut hasher);
+
+    cache_dir().map(|dir| dir.join(format!("{:016x}.bin", hasher.finish())))
+}
+
+fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let ptr = gl::GetString(name);
+        if ptr.is_null() {
+            return String::new();
+        }
+        StdCStr::from_ptr(ptr as *const _)
+            .to_string_lossy()
+            .into_owned()
+    }
+}